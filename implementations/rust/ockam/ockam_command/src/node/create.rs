@@ -0,0 +1,239 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use miette::IntoDiagnostic;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use ockam_node::Context;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::info;
+
+use crate::node::show::wait_until_node_is_up;
+use crate::node::util::{
+    build_create_args, from_inherit_fd_arg, restart_node, spawn_node, CgroupLimits,
+    InheritedListeners, OrchestratorKind, SandboxProfile,
+};
+use crate::shared_args::TrustOpts;
+use crate::{Command as CommandTrait, CommandGlobalOpts};
+
+/// Create a node
+#[derive(Clone, Debug, Args)]
+pub struct CreateCommand {
+    /// Name of the node
+    #[arg(default_value_t = default_node_name())]
+    pub name: String,
+
+    /// Address the node's TCP listener binds to
+    #[arg(long, value_name = "SOCKET_ADDRESS", default_value = "127.0.0.1:0")]
+    pub tcp_listener_address: String,
+
+    /// Address the node's UDP listener binds to
+    #[arg(long, value_name = "SOCKET_ADDRESS", default_value = "127.0.0.1:0")]
+    pub udp_listener_address: String,
+
+    /// Enable the UDP transport
+    #[arg(long)]
+    pub udp: bool,
+
+    /// Identity to run the node as
+    #[arg(long)]
+    pub identity: Option<String>,
+
+    #[arg(long, hide = true)]
+    pub skip_is_running_check: bool,
+
+    #[arg(long)]
+    pub no_status_endpoint: bool,
+
+    #[arg(long)]
+    pub status_endpoint_port: Option<u16>,
+
+    #[arg(long, value_name = "JSON")]
+    pub launch_configuration: Option<serde_json::Value>,
+
+    #[arg(long, hide = true)]
+    pub opentelemetry_context: Option<String>,
+
+    #[command(flatten)]
+    pub trust_opts: TrustOpts,
+
+    /// Run in the foreground instead of detaching to the background
+    #[arg(long)]
+    pub foreground: bool,
+
+    /// Marks this invocation as the detached process `spawn_node` launched,
+    /// as opposed to the outer CLI invocation the user ran
+    #[arg(long, hide = true)]
+    pub child_process: bool,
+
+    /// Inherit already-bound listening sockets from the parent, as
+    /// `tcp=<fd>,udp=<fd>`, instead of binding fresh ones. Set internally by
+    /// `spawn_node`/`restart_node` for zero-downtime restarts; not meant to
+    /// be passed by hand.
+    #[arg(long, hide = true)]
+    pub inherit_fd: Option<String>,
+
+    /// Which backend to launch this node's process with
+    #[arg(long, value_enum, default_value_t = OrchestratorKind::default())]
+    pub orchestrator: OrchestratorKind,
+
+    /// Maximum memory the node's cgroup may use (cgroup v2 `memory.max` syntax, e.g. "512M")
+    #[arg(long, value_name = "BYTES")]
+    pub memory_max: Option<String>,
+
+    /// CPU quota for the node's cgroup (cgroup v2 `cpu.max` syntax, e.g. "50000 100000")
+    #[arg(long)]
+    pub cpu_quota: Option<String>,
+
+    /// Maximum number of processes/threads the node's cgroup may spawn
+    #[arg(long)]
+    pub pids_max: Option<String>,
+
+    /// Drop capabilities and install a seccomp filter around the node process
+    #[arg(long)]
+    pub sandbox: bool,
+
+    /// Extra syscalls to allow under `--sandbox`, one per line. Except under
+    /// `--orchestrator systemd`, a name not already on the built-in allowlist
+    /// makes installing the seccomp filter fail.
+    #[arg(long, requires = "sandbox")]
+    pub sandbox_profile: Option<PathBuf>,
+
+    /// Keep the node's process attached and restart it with backoff on crash,
+    /// instead of detaching it to an orchestrator
+    #[arg(long)]
+    pub supervise: bool,
+}
+
+fn default_node_name() -> String {
+    hex::encode(rand::random::<[u8; 4]>())
+}
+
+impl Default for CreateCommand {
+    fn default() -> Self {
+        Self {
+            name: default_node_name(),
+            tcp_listener_address: "127.0.0.1:0".to_string(),
+            udp_listener_address: "127.0.0.1:0".to_string(),
+            udp: false,
+            identity: None,
+            skip_is_running_check: false,
+            no_status_endpoint: false,
+            status_endpoint_port: None,
+            launch_configuration: None,
+            opentelemetry_context: None,
+            trust_opts: TrustOpts::default(),
+            foreground: false,
+            child_process: false,
+            inherit_fd: None,
+            orchestrator: OrchestratorKind::default(),
+            memory_max: None,
+            cpu_quota: None,
+            pids_max: None,
+            sandbox: false,
+            sandbox_profile: None,
+            supervise: false,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for CreateCommand {
+    const NAME: &'static str = "node create";
+
+    async fn async_run(&self, ctx: &Context, opts: CommandGlobalOpts) -> miette::Result<()> {
+        if !self.child_process {
+            // Outer invocation: hand off to spawn_node, which launches the
+            // node (with `--foreground --child-process`) through the
+            // selected orchestrator, and wait for it to come up.
+            spawn_node(&opts, self.clone()).await?;
+            wait_until_node_is_up(ctx, &opts.state, &self.name).await?;
+            return Ok(());
+        }
+
+        // This is the child: reconstruct the listeners the parent already
+        // bound for us rather than binding fresh ones, if it handed us any.
+        let listeners = match &self.inherit_fd {
+            Some(value) => {
+                // SAFETY: `--inherit-fd` is only ever set by `spawn_node`/
+                // `restart_node`, which guarantee the descriptors it names
+                // are still-open listening sockets inherited into this
+                // process.
+                let (tcp, udp) = unsafe { from_inherit_fd_arg(value)? };
+                InheritedListeners { tcp, udp }
+            }
+            None => {
+                InheritedListeners::bind(&self.tcp_listener_address, &self.udp_listener_address)?
+            }
+        };
+
+        run_foreground(ctx, &opts, self, listeners).await
+    }
+}
+
+/// Run the node manager in the foreground on `listeners`, restarting it in
+/// place on `SIGHUP` and handing the inherited sockets down to the
+/// replacement so neither connections nor the listening port are dropped.
+async fn run_foreground(
+    ctx: &Context,
+    opts: &CommandGlobalOpts,
+    cmd: &CreateCommand,
+    listeners: InheritedListeners,
+) -> miette::Result<()> {
+    // The actual node manager bring-up (binding workers to `listeners`,
+    // starting the status endpoint, etc.) happens elsewhere and isn't
+    // reproduced here; this loop only owns the restart-on-`SIGHUP` wiring.
+    let mut sighup = signal(SignalKind::hangup()).into_diagnostic()?;
+    let mut sigterm = signal(SignalKind::terminate()).into_diagnostic()?;
+    let mut sigint = signal(SignalKind::interrupt()).into_diagnostic()?;
+
+    loop {
+        tokio::select! {
+            _ = sighup.recv() => {
+                info!(node = %cmd.name, "SIGHUP received, restarting node in place");
+
+                // Rebuild the same argument list the original `node create`
+                // invocation would have produced, against the listeners'
+                // current (already-bound) addresses, so the restarted node
+                // keeps its identity, trust configuration, launch config,
+                // and status-endpoint settings instead of coming back with
+                // only its name and `--udp`.
+                let (tcp_listener_address, udp_listener_address) = listeners.bound_addresses()?;
+                let restart_args =
+                    build_create_args(opts, cmd, &tcp_listener_address, &udp_listener_address)?;
+
+                let sandbox = if cmd.sandbox {
+                    Some(match &cmd.sandbox_profile {
+                        Some(path) => SandboxProfile::load(path)?,
+                        None => SandboxProfile::default(),
+                    })
+                } else {
+                    None
+                };
+
+                restart_node(
+                    ctx,
+                    opts,
+                    &cmd.name,
+                    Pid::from_raw(std::process::id() as i32),
+                    &listeners,
+                    restart_args,
+                    cmd.orchestrator,
+                    CgroupLimits {
+                        memory_max: cmd.memory_max.clone(),
+                        cpu_quota: cmd.cpu_quota.clone(),
+                        pids_max: cmd.pids_max.clone(),
+                    },
+                    sandbox,
+                )
+                .await?;
+                return Ok(());
+            }
+            _ = sigterm.recv() => {
+                let _ = kill(Pid::from_raw(std::process::id() as i32), Signal::SIGTERM);
+                return Ok(());
+            }
+            _ = sigint.recv() => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use clap::Args;
+use miette::IntoDiagnostic;
+use nix::sys::signal::{kill, Signal};
+use ockam_node::Context;
+use tokio::process::Command;
+
+use crate::node::util::{load_handle, NodeHandle};
+use crate::{Command as CommandTrait, CommandGlobalOpts};
+
+/// Restart a running node in place, without dropping in-flight connections
+/// or losing its listening port.
+///
+/// This is the same zero-downtime restart a node performs on its own in
+/// response to `SIGHUP`: the node rebuilds its original `node create`
+/// arguments, hands its listeners down to a replacement process, and only
+/// exits itself once the replacement is confirmed up. This command exists
+/// to trigger that restart from the outside, since an operator has no other
+/// way to send a node `SIGHUP`.
+#[derive(Clone, Debug, Args)]
+pub struct RestartCommand {
+    /// Name of the node to restart
+    pub name: String,
+}
+
+#[async_trait::async_trait]
+impl CommandTrait for RestartCommand {
+    const NAME: &'static str = "node restart";
+
+    async fn async_run(&self, _ctx: &Context, _opts: CommandGlobalOpts) -> miette::Result<()> {
+        match load_handle(&self.name)? {
+            // A process-backed node's own `run_foreground` loop is what
+            // actually performs the restart; delivering `SIGHUP` to it is
+            // all this command needs to do.
+            NodeHandle::Pid(pid) => kill(pid, Signal::SIGHUP).into_diagnostic(),
+            // systemd units run the same `run_foreground` loop under a
+            // different process tree; `systemctl kill` is the `--user`
+            // equivalent of sending the unit's main process a signal.
+            NodeHandle::Unit(unit) => {
+                let status = Command::new("systemctl")
+                    .args(["--user", "kill", "--signal=HUP", &unit])
+                    .status()
+                    .await
+                    .into_diagnostic()?;
+                if !status.success() {
+                    return Err(miette::miette!(
+                        "systemctl failed to signal unit {unit} for node {}",
+                        self.name
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use miette::Context as _;
 use miette::IntoDiagnostic;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
 use ockam_core::env::get_env_with_default;
 use ockam_node::Context;
 use rand::random;
@@ -14,6 +16,26 @@ use crate::run::parser::resource::utils::subprocess_stdio;
 use crate::shared_args::TrustOpts;
 use crate::{Command as CommandTrait, CommandGlobalOpts};
 
+mod cgroup;
+mod fd;
+mod orchestrator;
+mod sandbox;
+mod supervisor;
+
+pub use cgroup::CgroupLimits;
+pub use fd::{from_inherit_fd_arg, InheritedListeners};
+pub use orchestrator::{
+    load_handle, NodeHandle, NodeSpec, NodeStatus, Orchestrator, OrchestratorKind,
+};
+pub use sandbox::SandboxProfile;
+pub use supervisor::{load_status as load_supervisor_status, BackoffPolicy, SupervisorStatus};
+
+/// Remove a node's cgroup v2 subtree. Call this when the node is deleted so
+/// `/sys/fs/cgroup/ockam` doesn't accumulate an empty directory per node.
+pub fn cleanup_node_cgroup(node_name: &str) {
+    cgroup::cleanup(node_name)
+}
+
 pub struct NodeManagerDefaults {
     pub node_name: String,
     pub tcp_listener_address: String,
@@ -46,36 +68,19 @@ pub async fn initialize_default_node(
     Ok(())
 }
 
-/// Construct the argument list and re-execute the ockam
-/// CLI in foreground mode to start the newly created node
-#[allow(clippy::too_many_arguments)]
-pub async fn spawn_node(opts: &CommandGlobalOpts, cmd: CreateCommand) -> miette::Result<()> {
-    info!(
-        "preparing to spawn a new node with name {} in the background",
-        &cmd.name
-    );
-
-    let CreateCommand {
-        skip_is_running_check,
-        name,
-        identity: identity_name,
-        tcp_listener_address,
-        udp_listener_address,
-        no_status_endpoint,
-        status_endpoint_port,
-        udp,
-        launch_configuration,
-        trust_opts,
-        opentelemetry_context,
-        ..
-    } = cmd;
-    let TrustOpts {
-        project_name,
-        authority_identity,
-        authority_route,
-        credential_scope,
-    } = trust_opts;
-
+/// Build the `ockam node create` argument list that reproduces `cmd` (and
+/// the global options it was run with) exactly, against the given
+/// already-bound listener addresses. Shared by the initial spawn and by a
+/// later restart so the two can never drift apart: a node must come back
+/// with the same identity, trust configuration, and launch config it
+/// started with, not just the same name. `--orchestrator` and `--supervise`
+/// are not forwarded, so the child sees their defaults.
+pub(crate) fn build_create_args(
+    opts: &CommandGlobalOpts,
+    cmd: &CreateCommand,
+    tcp_listener_address: &str,
+    udp_listener_address: &str,
+) -> miette::Result<Vec<String>> {
     let mut args = vec![
         match opts.global_args.verbose {
             0 => "-vv".to_string(),
@@ -91,12 +96,12 @@ pub async fn spawn_node(opts: &CommandGlobalOpts, cmd: CreateCommand) -> miette:
         "--child-process".to_string(),
     ];
 
-    if let Some(credential_scope) = credential_scope {
+    if let Some(credential_scope) = &cmd.trust_opts.credential_scope {
         args.push("--credential-scope".to_string());
-        args.push(credential_scope)
+        args.push(credential_scope.clone())
     }
 
-    if skip_is_running_check {
+    if cmd.skip_is_running_check {
         args.push("--skip-is-running-check".to_string());
     }
 
@@ -104,52 +109,158 @@ pub async fn spawn_node(opts: &CommandGlobalOpts, cmd: CreateCommand) -> miette:
         args.push("--no-color".to_string());
     }
 
-    if let Some(identity_name) = identity_name {
+    if let Some(identity_name) = &cmd.identity {
         args.push("--identity".to_string());
-        args.push(identity_name);
+        args.push(identity_name.clone());
     }
 
-    if let Some(config) = launch_configuration {
+    if let Some(config) = &cmd.launch_configuration {
         args.push("--launch-config".to_string());
-        args.push(serde_json::to_string(&config).unwrap());
+        args.push(serde_json::to_string(config).unwrap());
     }
 
-    if let Some(project_name) = project_name {
+    if let Some(project_name) = &cmd.trust_opts.project_name {
         args.push("--project".to_string());
-        args.push(project_name);
+        args.push(project_name.clone());
     }
 
-    if let Some(authority_identity) = authority_identity {
+    if let Some(authority_identity) = &cmd.trust_opts.authority_identity {
         args.push("--authority-identity".to_string());
         args.push(authority_identity.export_as_string().into_diagnostic()?);
     }
 
-    if let Some(authority_route) = authority_route {
+    if let Some(authority_route) = &cmd.trust_opts.authority_route {
         args.push("--authority-route".to_string());
         args.push(authority_route.to_string());
     }
 
-    if let Some(opentelemetry_context) = opentelemetry_context {
+    if let Some(opentelemetry_context) = &cmd.opentelemetry_context {
         args.push("--opentelemetry-context".to_string());
         args.push(opentelemetry_context.to_string());
     }
 
-    if no_status_endpoint {
+    if cmd.no_status_endpoint {
         args.push("--no-status-endpoint".to_string());
     }
 
-    if let Some(status_endpoint_port) = status_endpoint_port {
+    if let Some(status_endpoint_port) = cmd.status_endpoint_port {
         args.push("--status-endpoint-port".to_string());
         args.push(status_endpoint_port.to_string());
     }
 
-    if udp {
+    if cmd.udp {
         args.push("--udp".to_string());
     }
 
-    args.push(name.to_owned());
+    // Forwarded so the child's own `run_foreground` hands the same cgroup
+    // limits and sandbox to its replacement on restart.
+    for (flag, value) in [
+        ("--memory-max", cmd.memory_max.clone()),
+        ("--cpu-quota", cmd.cpu_quota.clone()),
+        ("--pids-max", cmd.pids_max.clone()),
+        ("--sandbox-profile", cmd.sandbox_profile.as_ref().map(|p| p.display().to_string())),
+    ] {
+        if let Some(value) = value {
+            args.push(flag.to_string());
+            args.push(value);
+        }
+    }
+    if cmd.sandbox {
+        args.push("--sandbox".to_string());
+    }
+
+    args.push(cmd.name.clone());
+
+    Ok(args)
+}
+
+/// Construct the argument list and re-execute the ockam
+/// CLI in foreground mode to start the newly created node
+pub async fn spawn_node(opts: &CommandGlobalOpts, cmd: CreateCommand) -> miette::Result<()> {
+    info!(
+        "preparing to spawn a new node with name {} in the background",
+        &cmd.name
+    );
+
+    // Bind the listeners ourselves, rather than letting the child bind them,
+    // so the socket outlives this process and a future restart can inherit
+    // it instead of rebinding (and dropping in-flight connections along with
+    // the ephemeral port). The addresses are re-resolved from the live
+    // sockets since binding `127.0.0.1:0` fixes the port only now.
+    let listeners =
+        InheritedListeners::bind(&cmd.tcp_listener_address, &cmd.udp_listener_address)?;
+    let (tcp_listener_address, udp_listener_address) = listeners.bound_addresses()?;
+
+    let mut args = build_create_args(opts, &cmd, &tcp_listener_address, &udp_listener_address)?;
+    // A systemd unit is never a descendant of this process and can't inherit
+    // the listeners, so it is started without `--inherit-fd` and binds the
+    // already-resolved addresses itself once they are released below.
+    let inherit_listeners = cmd.supervise || cmd.orchestrator != OrchestratorKind::Systemd;
+    if inherit_listeners {
+        args.push("--inherit-fd".to_string());
+        args.push(listeners.inherit_fd_arg());
+    }
+
+    let CreateCommand {
+        name,
+        orchestrator,
+        memory_max,
+        cpu_quota,
+        pids_max,
+        sandbox,
+        sandbox_profile,
+        supervise,
+        ..
+    } = cmd;
 
-    run_ockam(args, opts.global_args.quiet).await
+    // Hand the fully-built argument list to whichever backend was selected:
+    // what node to run is decided above, how it is launched and supervised
+    // is entirely up to the orchestrator.
+    let ockam_exe = current_exe().unwrap_or_else(|_| {
+        get_env_with_default("OCKAM", "ockam".to_string())
+            .unwrap()
+            .into()
+    });
+    let node_name = name.clone();
+    let spec = NodeSpec {
+        node_name: name,
+        binary: ockam_exe,
+        args,
+        env: Default::default(),
+        tcp_listener_address,
+        udp_listener_address,
+        quiet: opts.global_args.quiet,
+        cgroup_limits: CgroupLimits {
+            memory_max,
+            cpu_quota,
+            pids_max,
+        },
+        sandbox: if sandbox {
+            Some(match sandbox_profile {
+                Some(path) => SandboxProfile::load(&path)?,
+                None => SandboxProfile::default(),
+            })
+        } else {
+            None
+        },
+    };
+
+    if supervise {
+        // Stay attached to the child in the foreground instead of handing
+        // it to an orchestrator: this call only returns once the node
+        // exits cleanly or the supervisor's circuit breaker gives up.
+        return supervisor::run_supervised(spec, BackoffPolicy::default()).await;
+    }
+
+    // Persisted so `ockam node stop`/`status` can reach this node again
+    // later through the same handle, whether it's a pid (`ProcessOrchestrator`)
+    // or a systemd unit (`SystemdOrchestrator`).
+    if !inherit_listeners {
+        drop(listeners);
+    }
+    let handle = orchestrator.build().ensure_running(spec).await?;
+    orchestrator::persist_handle(&node_name, &handle)?;
+    Ok(())
 }
 
 /// Run the ockam command line with specific arguments
@@ -184,3 +295,60 @@ pub async fn run_ockam(args: Vec<String>, quiet: bool) -> miette::Result<()> {
 
     Ok(())
 }
+
+/// Restart a running node in place, without dropping in-flight connections.
+///
+/// Called by a node in response to a reload request (`SIGHUP`, or an
+/// `ockam node restart` issued against it). `listeners` are the sockets this
+/// node has been accepting on since it started; they are handed to the
+/// replacement process via `--inherit-fd` so it can pick up the same port
+/// instead of binding a new one. The replacement is launched through
+/// `orchestrator`, with the same `cgroup_limits`/`sandbox` the node
+/// originally started with, so a restart can't silently drop protections a
+/// crash-restart (`run_supervised`) or a fresh `ensure_running` would still
+/// honor. The old process keeps accepting until the replacement reports
+/// itself as up, then drains and exits, so exactly one process is ever
+/// calling `accept()` on the listeners at a time.
+#[allow(clippy::too_many_arguments)]
+pub async fn restart_node(
+    ctx: &Context,
+    opts: &CommandGlobalOpts,
+    node_name: &str,
+    old_pid: Pid,
+    listeners: &InheritedListeners,
+    mut args: Vec<String>,
+    orchestrator: OrchestratorKind,
+    cgroup_limits: CgroupLimits,
+    sandbox: Option<SandboxProfile>,
+) -> miette::Result<()> {
+    args.push("--inherit-fd".to_string());
+    args.push(listeners.inherit_fd_arg());
+
+    let ockam_exe = current_exe().unwrap_or_else(|_| {
+        get_env_with_default("OCKAM", "ockam".to_string())
+            .unwrap()
+            .into()
+    });
+    let (tcp_listener_address, udp_listener_address) = listeners.bound_addresses()?;
+    let spec = NodeSpec {
+        node_name: node_name.to_string(),
+        binary: ockam_exe,
+        args,
+        env: Default::default(),
+        tcp_listener_address,
+        udp_listener_address,
+        quiet: opts.global_args.quiet,
+        cgroup_limits,
+        sandbox,
+    };
+
+    let handle = orchestrator.build().ensure_running(spec).await?;
+    orchestrator::persist_handle(node_name, &handle)?;
+    wait_until_node_is_up(ctx, &opts.state, node_name).await?;
+
+    // The replacement is confirmed up and accepting connections on the
+    // inherited sockets: tell the old process to drain and exit.
+    kill(old_pid, Signal::SIGTERM).into_diagnostic()?;
+
+    Ok(())
+}
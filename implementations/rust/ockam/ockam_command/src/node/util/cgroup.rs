@@ -0,0 +1,124 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use miette::Context as _;
+use miette::IntoDiagnostic;
+use tracing::warn;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup/ockam";
+
+/// Resource limits applied to a node's process tree via a dedicated cgroup
+/// v2 subtree, so a single node can't starve the rest of a shared host.
+#[derive(Clone, Debug, Default)]
+pub struct CgroupLimits {
+    pub memory_max: Option<String>,
+    pub cpu_quota: Option<String>,
+    pub pids_max: Option<String>,
+}
+
+impl CgroupLimits {
+    pub fn is_empty(&self) -> bool {
+        self.memory_max.is_none() && self.cpu_quota.is_none() && self.pids_max.is_none()
+    }
+}
+
+fn subtree_path(node_name: &str) -> PathBuf {
+    Path::new(CGROUP_ROOT).join(node_name)
+}
+
+/// Create `/sys/fs/cgroup/ockam/<node_name>` and write the requested
+/// `memory.max`, `cpu.max` and `pids.max` control files.
+///
+/// Cgroup v2 may not be available (older kernel, or this process lacks
+/// delegation permission on `/sys/fs/cgroup/ockam`); when it isn't, this
+/// warns and returns `None` rather than failing node creation outright.
+pub fn prepare(node_name: &str, limits: &CgroupLimits) -> Option<PathBuf> {
+    if limits.is_empty() {
+        return None;
+    }
+
+    let path = subtree_path(node_name);
+    if let Err(err) = fs::create_dir_all(&path) {
+        warn!(%err, "cgroup v2 unavailable, starting node without resource limits");
+        return None;
+    }
+
+    for (file, value) in [
+        ("memory.max", &limits.memory_max),
+        ("cpu.max", &limits.cpu_quota),
+        ("pids.max", &limits.pids_max),
+    ] {
+        if let Some(value) = value {
+            if let Err(err) = fs::write(path.join(file), value) {
+                warn!(%err, file, "failed to write cgroup limit, continuing without it");
+            }
+        }
+    }
+
+    Some(path)
+}
+
+/// Place the calling process into `path`'s `cgroup.procs`.
+///
+/// Must be called from inside the child, in `pre_exec`, before `exec` -
+/// that's the only point at which the limits are guaranteed to apply from
+/// the node's first instruction.
+pub fn join_self(path: &Path) -> io::Result<()> {
+    fs::write(path.join("cgroup.procs"), std::process::id().to_string())
+}
+
+/// Convert a `--cpu-quota` value, given in cgroup v2 `cpu.max` syntax
+/// (`"<quota> <period>"` in microseconds, period defaulting to `100000`, or
+/// `"max"` for no limit), into the percentage string systemd's `CPUQuota=`
+/// unit property expects instead (e.g. `"20%"`).
+///
+/// `SystemdOrchestrator` has no `cpu.max` file to write the raw value to, so
+/// without this conversion the same `--cpu-quota "50000 100000"` that means
+/// "50%" to `ProcessOrchestrator` would be forwarded verbatim into
+/// `CPUQuota=`, which systemd would reject or misinterpret as an absurd
+/// percentage.
+pub fn cpu_quota_to_systemd_percent(cpu_quota: &str) -> miette::Result<Option<String>> {
+    let mut parts = cpu_quota.split_whitespace();
+    let quota = parts
+        .next()
+        .ok_or_else(|| miette::miette!("empty --cpu-quota value"))?;
+    if quota == "max" {
+        return Ok(None);
+    }
+    let quota: u64 = quota
+        .parse()
+        .into_diagnostic()
+        .wrap_err("invalid --cpu-quota: quota must be a number of microseconds or \"max\"")?;
+    let period: u64 = match parts.next() {
+        Some(period) => period
+            .parse()
+            .into_diagnostic()
+            .wrap_err("invalid --cpu-quota: period must be a number of microseconds")?,
+        None => 100_000,
+    };
+    if parts.next().is_some() {
+        return Err(miette::miette!(
+            "invalid --cpu-quota: expected \"<quota> <period>\", got \"{cpu_quota}\""
+        ));
+    }
+    if period == 0 {
+        return Err(miette::miette!(
+            "invalid --cpu-quota: period must be non-zero"
+        ));
+    }
+    // Round up so a quota that doesn't evenly divide the period still gets
+    // at least as much CPU time as the cgroup v2 value would have granted.
+    let percent = (quota * 100).div_ceil(period).max(1);
+    Ok(Some(format!("{percent}%")))
+}
+
+/// Remove a node's cgroup subtree, called when the node is deleted.
+pub fn cleanup(node_name: &str) {
+    let path = subtree_path(node_name);
+    if path.exists() {
+        if let Err(err) = fs::remove_dir(&path) {
+            warn!(%err, node_name, "failed to remove cgroup subtree");
+        }
+    }
+}
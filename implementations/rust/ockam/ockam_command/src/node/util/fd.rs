@@ -0,0 +1,131 @@
+use std::net::{TcpListener, UdpSocket};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+use miette::Context as _;
+use miette::IntoDiagnostic;
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+
+/// The listening sockets a node hands down to its replacement across a
+/// graceful restart.
+///
+/// The parent binds these once, up front, and hands them to the node process,
+/// which keeps them open for as long as it runs: a restart re-execs the binary with the descriptor
+/// numbers passed through [`inherit_fd_arg`](Self::inherit_fd_arg), so the
+/// replacement process can pick up exactly where the old one left off
+/// instead of binding a fresh socket and losing the port (and any
+/// connections still in flight).
+pub struct InheritedListeners {
+    pub tcp: TcpListener,
+    pub udp: UdpSocket,
+}
+
+impl InheritedListeners {
+    /// Bind both listeners and clear `FD_CLOEXEC` so they survive the `exec`
+    /// of a future restart.
+    pub fn bind(tcp_address: &str, udp_address: &str) -> miette::Result<Self> {
+        let tcp = TcpListener::bind(tcp_address)
+            .into_diagnostic()
+            .wrap_err("failed to bind the tcp listener")?;
+        let udp = UdpSocket::bind(udp_address)
+            .into_diagnostic()
+            .wrap_err("failed to bind the udp listener")?;
+        clear_cloexec(tcp.as_raw_fd())?;
+        clear_cloexec(udp.as_raw_fd())?;
+        Ok(Self { tcp, udp })
+    }
+
+    /// The `--inherit-fd tcp=<n>,udp=<m>` argument to pass to the child.
+    pub fn inherit_fd_arg(&self) -> String {
+        format!("tcp={},udp={}", self.tcp.as_raw_fd(), self.udp.as_raw_fd())
+    }
+
+    /// The addresses the sockets are actually bound to, now that ephemeral
+    /// ports (if any) have been resolved by the first bind. A restart must
+    /// re-resolve the listener address from the live socket rather than
+    /// reusing the original `127.0.0.1:0`, since the port is now fixed.
+    pub fn bound_addresses(&self) -> miette::Result<(String, String)> {
+        Ok((
+            self.tcp.local_addr().into_diagnostic()?.to_string(),
+            self.udp.local_addr().into_diagnostic()?.to_string(),
+        ))
+    }
+}
+
+fn clear_cloexec(fd: RawFd) -> miette::Result<()> {
+    let flags = fcntl(fd, FcntlArg::F_GETFD).into_diagnostic()?;
+    let flags = FdFlag::from_bits_truncate(flags) & !FdFlag::FD_CLOEXEC;
+    fcntl(fd, FcntlArg::F_SETFD(flags)).into_diagnostic()?;
+    Ok(())
+}
+
+/// Parse a `tcp=<n>,udp=<m>` value produced by
+/// [`InheritedListeners::inherit_fd_arg`] and reconstruct the listening
+/// sockets from the inherited descriptors, to be called by the child
+/// instead of binding.
+///
+/// # Safety
+/// The caller must guarantee that `value` names descriptors that are open,
+/// valid listening sockets in this process (i.e. it was produced by a
+/// parent that passed `--inherit-fd` and is still holding them open), and
+/// that this is only called once per descriptor.
+pub unsafe fn from_inherit_fd_arg(value: &str) -> miette::Result<(TcpListener, UdpSocket)> {
+    let mut tcp_fd = None;
+    let mut udp_fd = None;
+    for entry in value.split(',') {
+        let (key, fd) = entry
+            .split_once('=')
+            .ok_or_else(|| miette::miette!("invalid --inherit-fd entry: {entry}"))?;
+        let fd: RawFd = fd
+            .parse()
+            .into_diagnostic()
+            .wrap_err("invalid --inherit-fd descriptor number")?;
+        match key {
+            "tcp" => tcp_fd = Some(fd),
+            "udp" => udp_fd = Some(fd),
+            other => return Err(miette::miette!("unknown --inherit-fd key: {other}")),
+        }
+    }
+    let tcp_fd = tcp_fd.ok_or_else(|| miette::miette!("--inherit-fd is missing a tcp= entry"))?;
+    let udp_fd = udp_fd.ok_or_else(|| miette::miette!("--inherit-fd is missing a udp= entry"))?;
+    Ok((
+        TcpListener::from_raw_fd(tcp_fd),
+        UdpSocket::from_raw_fd(udp_fd),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Every case below is rejected while still parsing `value`, before
+    // `from_inherit_fd_arg` ever reaches its `unsafe` block, so calling it
+    // directly here doesn't risk touching an invalid descriptor.
+
+    #[test]
+    fn rejects_a_missing_udp_entry() {
+        let err = unsafe { from_inherit_fd_arg("tcp=3") }.unwrap_err();
+        assert!(err.to_string().contains("udp="));
+    }
+
+    #[test]
+    fn rejects_a_missing_tcp_entry() {
+        let err = unsafe { from_inherit_fd_arg("udp=4") }.unwrap_err();
+        assert!(err.to_string().contains("tcp="));
+    }
+
+    #[test]
+    fn rejects_an_unknown_key() {
+        let err = unsafe { from_inherit_fd_arg("tcp=3,udp=4,quic=5") }.unwrap_err();
+        assert!(err.to_string().contains("quic"));
+    }
+
+    #[test]
+    fn rejects_a_non_numeric_descriptor() {
+        assert!(unsafe { from_inherit_fd_arg("tcp=not-a-number,udp=4") }.is_err());
+    }
+
+    #[test]
+    fn rejects_an_entry_with_no_separator() {
+        assert!(unsafe { from_inherit_fd_arg("tcp=3,udp") }.is_err());
+    }
+}
@@ -0,0 +1,158 @@
+mod process;
+mod systemd;
+
+pub use process::ProcessOrchestrator;
+pub use systemd::SystemdOrchestrator;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use miette::IntoDiagnostic;
+use nix::unistd::Pid;
+
+use super::cgroup::CgroupLimits;
+use super::sandbox::SandboxProfile;
+
+/// Everything an [`Orchestrator`] needs to launch a node, independent of how
+/// it is actually supervised.
+///
+/// This is the backend-agnostic counterpart of the argument list `spawn_node`
+/// used to build directly: a description of *what* to run, handed to
+/// whichever backend decides *how* to run it.
+#[derive(Clone, Debug)]
+pub struct NodeSpec {
+    pub node_name: String,
+    pub binary: PathBuf,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub tcp_listener_address: String,
+    pub udp_listener_address: String,
+    pub quiet: bool,
+    pub cgroup_limits: CgroupLimits,
+    pub sandbox: Option<SandboxProfile>,
+}
+
+/// A handle to a node that was launched through an [`Orchestrator`].
+///
+/// What it actually points to depends on the backend: a `ProcessOrchestrator`
+/// hands back the child's pid, while a supervised backend (systemd, a future
+/// container orchestrator) hands back a unit or container identifier. Either
+/// way it's enough to ask the backend to stop the node or check on it again
+/// later.
+#[derive(Clone, Debug)]
+pub enum NodeHandle {
+    Pid(Pid),
+    Unit(String),
+}
+
+impl NodeHandle {
+    fn to_persisted(&self) -> String {
+        match self {
+            Self::Pid(pid) => format!("pid:{pid}"),
+            Self::Unit(unit) => format!("unit:{unit}"),
+        }
+    }
+
+    fn from_persisted(value: &str) -> miette::Result<Self> {
+        let (kind, value) = value
+            .split_once(':')
+            .ok_or_else(|| miette::miette!("invalid orchestrator handle: {value}"))?;
+        match kind {
+            "pid" => Ok(Self::Pid(Pid::from_raw(
+                value.parse().into_diagnostic()?,
+            ))),
+            "unit" => Ok(Self::Unit(value.to_string())),
+            other => Err(miette::miette!("unknown orchestrator handle kind: {other}")),
+        }
+    }
+}
+
+fn handle_path(node_name: &str) -> PathBuf {
+    let ockam_home = std::env::var("OCKAM_HOME").unwrap_or_else(|_| {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        format!("{home}/.ockam")
+    });
+    Path::new(&ockam_home)
+        .join("nodes")
+        .join(node_name)
+        .join("orchestrator_handle")
+}
+
+/// Persist the [`NodeHandle`] an [`Orchestrator`] handed back for `node_name`,
+/// so a later `ockam node stop`/`status` can reach it regardless of which
+/// backend actually launched the node.
+pub fn persist_handle(node_name: &str, handle: &NodeHandle) -> miette::Result<()> {
+    let path = handle_path(node_name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).into_diagnostic()?;
+    }
+    fs::write(path, handle.to_persisted()).into_diagnostic()?;
+    Ok(())
+}
+
+/// Load the [`NodeHandle`] previously saved by [`persist_handle`] for
+/// `node_name`.
+pub fn load_handle(node_name: &str) -> miette::Result<NodeHandle> {
+    let contents = fs::read_to_string(handle_path(node_name)).into_diagnostic()?;
+    NodeHandle::from_persisted(contents.trim())
+}
+
+/// The current state of a node, as last observed by its [`Orchestrator`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NodeStatus {
+    Running,
+    Stopped,
+    Unknown,
+}
+
+/// Launches and supervises `ockam node` processes.
+///
+/// Implementations decide *how* a [`NodeSpec`] becomes a running node: a bare
+/// detached process, a systemd unit, or (in the future) a container. Callers
+/// only ever deal with the resulting [`NodeHandle`].
+#[async_trait::async_trait]
+pub trait Orchestrator: Send + Sync {
+    /// Start the node described by `spec`. No check is made for an instance
+    /// that is already running: every call launches a new one.
+    async fn ensure_running(&self, spec: NodeSpec) -> miette::Result<NodeHandle>;
+
+    /// Stop a previously started node.
+    async fn stop(&self, handle: &NodeHandle) -> miette::Result<()>;
+
+    /// Query whether a node is still running.
+    async fn status(&self, handle: &NodeHandle) -> miette::Result<NodeStatus>;
+}
+
+/// Which [`Orchestrator`] backend to launch a node with, selected via
+/// `ockam node create --orchestrator <kind>`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum OrchestratorKind {
+    /// Detached process managed directly by the CLI (today's behavior).
+    #[default]
+    Process,
+    /// Transient systemd unit, supervised and restarted by systemd.
+    Systemd,
+}
+
+impl FromStr for OrchestratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "process" => Ok(Self::Process),
+            "systemd" => Ok(Self::Systemd),
+            other => Err(format!("unknown orchestrator: {other}")),
+        }
+    }
+}
+
+impl OrchestratorKind {
+    pub fn build(self) -> Box<dyn Orchestrator> {
+        match self {
+            Self::Process => Box::new(ProcessOrchestrator),
+            Self::Systemd => Box::new(SystemdOrchestrator),
+        }
+    }
+}
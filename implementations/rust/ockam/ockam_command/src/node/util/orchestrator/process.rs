@@ -0,0 +1,73 @@
+use miette::IntoDiagnostic;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::process::Stdio;
+
+use super::super::cgroup;
+use super::{NodeHandle, NodeSpec, NodeStatus, Orchestrator};
+use crate::run::parser::resource::utils::subprocess_stdio;
+
+/// Reproduces today's behavior: a detached, `setsid`-ed child process that
+/// the CLI loses track of once spawned.
+pub struct ProcessOrchestrator;
+
+#[async_trait::async_trait]
+impl Orchestrator for ProcessOrchestrator {
+    async fn ensure_running(&self, spec: NodeSpec) -> miette::Result<NodeHandle> {
+        // Create the node's cgroup subtree up front so the child can be
+        // placed into it from `pre_exec`, before it execs into the node
+        // binary, and its limits apply from the very first instruction.
+        let cgroup_path = cgroup::prepare(&spec.node_name, &spec.cgroup_limits);
+        let sandbox = spec.sandbox.clone();
+
+        let mut command = std::process::Command::new(&spec.binary);
+        command
+            .args(&spec.args)
+            .envs(&spec.env)
+            .stdout(subprocess_stdio(spec.quiet))
+            .stderr(subprocess_stdio(spec.quiet))
+            .stdin(Stdio::null());
+
+        let child = unsafe {
+            use std::os::unix::prelude::CommandExt;
+            command
+                .pre_exec(move || {
+                    nix::unistd::setsid().map_err(std::io::Error::from)?;
+                    if let Some(path) = &cgroup_path {
+                        cgroup::join_self(path)?;
+                    }
+                    // The sandbox is installed last, after cgroup
+                    // placement, so writing to `cgroup.procs` above isn't
+                    // at risk of being blocked by its own seccomp filter.
+                    if let Some(sandbox) = &sandbox {
+                        sandbox.apply()?;
+                    }
+                    Ok(())
+                })
+                .spawn()
+                .into_diagnostic()?
+        };
+
+        Ok(NodeHandle::Pid(Pid::from_raw(child.id() as i32)))
+    }
+
+    async fn stop(&self, handle: &NodeHandle) -> miette::Result<()> {
+        match handle {
+            NodeHandle::Pid(pid) => kill(*pid, Signal::SIGTERM).into_diagnostic(),
+            NodeHandle::Unit(unit) => Err(miette::miette!(
+                "ProcessOrchestrator cannot stop unit handle {unit}"
+            )),
+        }
+    }
+
+    async fn status(&self, handle: &NodeHandle) -> miette::Result<NodeStatus> {
+        match handle {
+            NodeHandle::Pid(pid) => match kill(*pid, None) {
+                Ok(()) => Ok(NodeStatus::Running),
+                Err(nix::errno::Errno::ESRCH) => Ok(NodeStatus::Stopped),
+                Err(_) => Ok(NodeStatus::Unknown),
+            },
+            NodeHandle::Unit(_) => Ok(NodeStatus::Unknown),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use miette::IntoDiagnostic;
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::super::cgroup;
+use super::{NodeHandle, NodeSpec, NodeStatus, Orchestrator};
+
+/// Registers a node as a transient systemd unit, via `systemd-run`, so it is
+/// supervised and restarted by systemd rather than by this CLI.
+pub struct SystemdOrchestrator;
+
+impl SystemdOrchestrator {
+    fn unit_name(node_name: &str) -> String {
+        format!("ockam-node-{node_name}.service")
+    }
+}
+
+#[async_trait::async_trait]
+impl Orchestrator for SystemdOrchestrator {
+    async fn ensure_running(&self, spec: NodeSpec) -> miette::Result<NodeHandle> {
+        // `systemd-run` asks the systemd manager to fork+exec the unit, so
+        // the resulting process is never a descendant of the CLI/supervisor
+        // holding `spec`'s listeners open: the fd numbers in `--inherit-fd`
+        // would refer to whatever (if anything) the new process happens to
+        // have open under those numbers, not the kept-alive listener. Fail
+        // loudly instead of silently launching a node that can't actually
+        // accept on the address it reports.
+        if spec.args.iter().any(|arg| arg == "--inherit-fd") {
+            return Err(miette::miette!(
+                "SystemdOrchestrator cannot honor --inherit-fd: a systemd-run unit is never a \
+                 descendant of this process, so inherited listener fds would not carry over. \
+                 Use --orchestrator process for zero-downtime restarts, or stop and recreate the \
+                 node instead."
+            ));
+        }
+
+        let unit = Self::unit_name(&spec.node_name);
+
+        let mut command = Command::new("systemd-run");
+        command
+            .arg("--user")
+            .arg("--collect")
+            .arg(format!("--unit={unit}"))
+            .arg(format!(
+                "--description=ockam node {name}",
+                name = spec.node_name
+            ))
+            .arg("--property=Restart=on-failure");
+
+        if let Some(memory_max) = &spec.cgroup_limits.memory_max {
+            command.arg(format!("--property=MemoryMax={memory_max}"));
+        }
+        if let Some(cpu_quota) = &spec.cgroup_limits.cpu_quota {
+            // `cpu_quota` is in cgroup v2 `cpu.max` syntax; systemd's
+            // `CPUQuota=` property expects a percentage instead.
+            if let Some(percent) = cgroup::cpu_quota_to_systemd_percent(cpu_quota)? {
+                command.arg(format!("--property=CPUQuota={percent}"));
+            }
+        }
+        if let Some(pids_max) = &spec.cgroup_limits.pids_max {
+            command.arg(format!("--property=TasksMax={pids_max}"));
+        }
+
+        if let Some(sandbox) = &spec.sandbox {
+            command.arg("--property=NoNewPrivileges=yes");
+            command.arg("--property=CapabilityBoundingSet=");
+            command.arg(format!(
+                "--property=SystemCallFilter={}",
+                sandbox.allowed_syscalls().join(" ")
+            ));
+        }
+
+        for (key, value) in &spec.env {
+            command.arg(format!("--setenv={key}={value}"));
+        }
+
+        command
+            .arg("--")
+            .arg(&spec.binary)
+            .args(&spec.args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let status = command.status().await.into_diagnostic()?;
+        if !status.success() {
+            return Err(miette::miette!(
+                "systemd-run failed to register unit {unit} for node {name}",
+                name = spec.node_name
+            ));
+        }
+
+        Ok(NodeHandle::Unit(unit))
+    }
+
+    async fn stop(&self, handle: &NodeHandle) -> miette::Result<()> {
+        let unit = match handle {
+            NodeHandle::Unit(unit) => unit,
+            NodeHandle::Pid(pid) => {
+                return Err(miette::miette!(
+                    "SystemdOrchestrator cannot stop pid handle {pid}"
+                ))
+            }
+        };
+
+        let status = Command::new("systemctl")
+            .args(["--user", "stop", unit])
+            .status()
+            .await
+            .into_diagnostic()?;
+        if !status.success() {
+            return Err(miette::miette!("systemctl failed to stop unit {unit}"));
+        }
+        Ok(())
+    }
+
+    async fn status(&self, handle: &NodeHandle) -> miette::Result<NodeStatus> {
+        let unit = match handle {
+            NodeHandle::Unit(unit) => unit,
+            NodeHandle::Pid(_) => return Ok(NodeStatus::Unknown),
+        };
+
+        let output = Command::new("systemctl")
+            .args(["--user", "is-active", unit])
+            .output()
+            .await
+            .into_diagnostic()?;
+
+        Ok(match String::from_utf8_lossy(&output.stdout).trim() {
+            "active" | "activating" => NodeStatus::Running,
+            "inactive" | "failed" => NodeStatus::Stopped,
+            _ => NodeStatus::Unknown,
+        })
+    }
+}
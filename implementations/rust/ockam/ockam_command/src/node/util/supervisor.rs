@@ -0,0 +1,272 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use miette::IntoDiagnostic;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, warn};
+
+use super::cgroup;
+use super::orchestrator::NodeSpec;
+use crate::run::parser::resource::utils::subprocess_stdio;
+
+/// Exponential backoff with a cap, plus a circuit breaker that gives up
+/// once too many failures land inside a sliding window.
+#[derive(Clone, Copy, Debug)]
+pub struct BackoffPolicy {
+    pub initial: Duration,
+    pub max: Duration,
+    pub max_failures: u32,
+    pub window: Duration,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            max_failures: 5,
+            window: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Restart count and last observed exit status for a supervised node,
+/// surfaced through the node's status endpoint.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SupervisorStatus {
+    pub restart_count: u32,
+    pub last_exit_status: Option<i32>,
+}
+
+fn status_path(node_name: &str) -> PathBuf {
+    let ockam_home = std::env::var("OCKAM_HOME").unwrap_or_else(|_| {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        format!("{home}/.ockam")
+    });
+    PathBuf::from(ockam_home)
+        .join("nodes")
+        .join(node_name)
+        .join("supervisor_status")
+}
+
+/// Persist `status` so the node's status endpoint can read it back without
+/// needing a channel into this supervisor loop.
+fn persist_status(node_name: &str, status: &SupervisorStatus) -> miette::Result<()> {
+    let path = status_path(node_name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).into_diagnostic()?;
+    }
+    fs::write(path, serde_json::to_string(status).into_diagnostic()?).into_diagnostic()?;
+    Ok(())
+}
+
+/// Load the [`SupervisorStatus`] last persisted by [`persist_status`] for
+/// `node_name`, for the status endpoint to include in its response.
+pub fn load_status(node_name: &str) -> miette::Result<SupervisorStatus> {
+    let contents = fs::read_to_string(status_path(node_name)).into_diagnostic()?;
+    serde_json::from_str(&contents).into_diagnostic()
+}
+
+/// Keeps a node's child process attached and supervised instead of
+/// detaching it: the child is reaped and restarted with exponential
+/// backoff on any abnormal exit, up to the circuit breaker in `policy`.
+/// `SIGTERM`/`SIGINT` delivered to this process are forwarded to the child
+/// for a graceful shutdown; `SIGHUP` is treated as an explicit restart
+/// request rather than a crash, and doesn't count against the breaker.
+pub async fn run_supervised(spec: NodeSpec, policy: BackoffPolicy) -> miette::Result<()> {
+    let mut status = SupervisorStatus::default();
+    let mut failures: Vec<Instant> = Vec::new();
+    let mut backoff = policy.initial;
+
+    // Created once, up front, like `ProcessOrchestrator` does: every
+    // restart below joins the same subtree rather than re-creating it.
+    let cgroup_path = cgroup::prepare(&spec.node_name, &spec.cgroup_limits);
+    let sandbox = spec.sandbox.clone();
+
+    let mut sigterm = signal(SignalKind::terminate()).into_diagnostic()?;
+    let mut sigint = signal(SignalKind::interrupt()).into_diagnostic()?;
+    let mut sighup = signal(SignalKind::hangup()).into_diagnostic()?;
+
+    loop {
+        let mut command = Command::new(&spec.binary);
+        command
+            .args(&spec.args)
+            .envs(&spec.env)
+            .stdout(subprocess_stdio(spec.quiet))
+            .stderr(subprocess_stdio(spec.quiet))
+            .stdin(Stdio::null());
+
+        let cgroup_path = cgroup_path.clone();
+        let sandbox = sandbox.clone();
+        // SAFETY: the closure only calls async-signal-safe operations
+        // (writing to an already-open fd, a syscall), same as
+        // `ProcessOrchestrator::ensure_running`.
+        let mut child = unsafe {
+            use std::os::unix::prelude::CommandExt;
+            command
+                .pre_exec(move || {
+                    if let Some(path) = &cgroup_path {
+                        cgroup::join_self(path)?;
+                    }
+                    // Installed last, after cgroup placement, for the same
+                    // reason as in `ProcessOrchestrator`.
+                    if let Some(sandbox) = &sandbox {
+                        sandbox.apply()?;
+                    }
+                    Ok(())
+                })
+                .spawn()
+                .into_diagnostic()?
+        };
+        let pid = Pid::from_raw(child.id().expect("just spawned, pid is set") as i32);
+        info!(node = %spec.node_name, %pid, "supervised node started");
+
+        let (exit_status, shutting_down) = tokio::select! {
+            result = child.wait() => (result.into_diagnostic()?, false),
+            _ = sigterm.recv() => (forward_and_wait(&mut child, pid, Signal::SIGTERM).await?, true),
+            _ = sigint.recv() => (forward_and_wait(&mut child, pid, Signal::SIGINT).await?, true),
+            _ = sighup.recv() => {
+                info!(node = %spec.node_name, "SIGHUP received, restarting supervised node");
+                let _ = forward_and_wait(&mut child, pid, Signal::SIGTERM).await;
+                status.restart_count += 1;
+                persist_status(&spec.node_name, &status)?;
+                continue;
+            }
+        };
+
+        status.last_exit_status = exit_status.code();
+        persist_status(&spec.node_name, &status)?;
+        if shutting_down {
+            info!(node = %spec.node_name, "supervised node shut down on request, not restarting");
+            return Ok(());
+        }
+        if exit_status.success() {
+            info!(node = %spec.node_name, "supervised node exited cleanly, not restarting");
+            return Ok(());
+        }
+
+        if record_failure(&mut failures, Instant::now(), &policy) {
+            return Err(miette::miette!(
+                "node {} crashed {} times within {:?}, giving up",
+                spec.node_name,
+                failures.len(),
+                policy.window
+            ));
+        }
+
+        status.restart_count += 1;
+        persist_status(&spec.node_name, &status)?;
+        warn!(
+            node = %spec.node_name,
+            ?exit_status,
+            ?backoff,
+            restarts = status.restart_count,
+            "supervised node exited abnormally, restarting"
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = next_backoff(backoff, &policy);
+    }
+}
+
+/// Double `current`, capped at `policy.max`.
+fn next_backoff(current: Duration, policy: &BackoffPolicy) -> Duration {
+    (current * 2).min(policy.max)
+}
+
+/// Record a failure at `now`, first pruning entries that have aged out of
+/// `policy.window`, and report whether the circuit breaker should trip
+/// (strictly more than `policy.max_failures` within the window).
+fn record_failure(failures: &mut Vec<Instant>, now: Instant, policy: &BackoffPolicy) -> bool {
+    failures.retain(|seen| now.duration_since(*seen) < policy.window);
+    failures.push(now);
+    failures.len() as u32 > policy.max_failures
+}
+
+async fn forward_and_wait(
+    child: &mut tokio::process::Child,
+    pid: Pid,
+    signal: Signal,
+) -> miette::Result<ExitStatus> {
+    kill(pid, signal).into_diagnostic()?;
+    child.wait().await.into_diagnostic()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn policy() -> BackoffPolicy {
+        BackoffPolicy {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            max_failures: 3,
+            window: Duration::from_secs(300),
+        }
+    }
+
+    #[test]
+    fn next_backoff_doubles_each_time() {
+        let policy = policy();
+        let mut backoff = policy.initial;
+        backoff = next_backoff(backoff, &policy);
+        assert_eq!(backoff, Duration::from_secs(2));
+        backoff = next_backoff(backoff, &policy);
+        assert_eq!(backoff, Duration::from_secs(4));
+        backoff = next_backoff(backoff, &policy);
+        assert_eq!(backoff, Duration::from_secs(8));
+    }
+
+    #[test]
+    fn next_backoff_caps_at_policy_max() {
+        let policy = policy();
+        let mut backoff = policy.max;
+        for _ in 0..3 {
+            backoff = next_backoff(backoff, &policy);
+        }
+        assert_eq!(backoff, policy.max);
+    }
+
+    #[test]
+    fn record_failure_does_not_trip_under_the_threshold() {
+        let policy = policy();
+        let mut failures = Vec::new();
+        let now = Instant::now();
+        assert!(!record_failure(&mut failures, now, &policy));
+        assert!(!record_failure(&mut failures, now, &policy));
+        assert!(!record_failure(&mut failures, now, &policy));
+        assert_eq!(failures.len(), 3);
+    }
+
+    #[test]
+    fn record_failure_trips_once_past_max_failures_within_the_window() {
+        let policy = policy();
+        let mut failures = Vec::new();
+        let now = Instant::now();
+        for _ in 0..policy.max_failures {
+            assert!(!record_failure(&mut failures, now, &policy));
+        }
+        assert!(record_failure(&mut failures, now, &policy));
+    }
+
+    #[test]
+    fn record_failure_ignores_entries_that_aged_out_of_the_window() {
+        let policy = policy();
+        let mut failures = Vec::new();
+        let long_ago = Instant::now() - (policy.window + Duration::from_secs(1));
+        for _ in 0..policy.max_failures {
+            failures.push(long_ago);
+        }
+
+        // All the old failures are outside the window, so a fresh one
+        // shouldn't trip the breaker even though the vector already holds
+        // `max_failures` entries.
+        assert!(!record_failure(&mut failures, Instant::now(), &policy));
+        assert_eq!(failures.len(), 1);
+    }
+}
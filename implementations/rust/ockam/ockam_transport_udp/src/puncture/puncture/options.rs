@@ -1,15 +1,31 @@
-use ockam_core::compat::sync::Arc;
-use ockam_core::flow_control::{FlowControlId, FlowControls};
-use ockam_core::{Address, AllowAll, OutgoingAccessControl, Result};
+use ockam_core::compat::sync::{Arc, RwLock};
+use ockam_core::flow_control::{FlowControlId, FlowControlOutgoingAccessControl, FlowControls};
+use ockam_core::{Address, OutgoingAccessControl, Result};
 
 use crate::puncture::puncture::Addresses;
 use core::fmt;
 use core::fmt::Formatter;
 
+mod ice;
+
+pub use ice::{
+    candidate_pairs, run_connectivity_checks, Candidate, CandidateKind, CandidateList, IceConfig,
+    NominatedPair,
+};
+
 /// Options for a UDP puncture
 pub struct UdpPunctureOptions {
     pub(crate) flow_control_id: FlowControlId,
-    pub(crate) _spawner_flow_control_id: Option<FlowControlId>, // FIXME: PUNCTURE
+    // The flow control id of the spawner that created this puncture's
+    // consumer, if any. Threaded into `create_receiver_outgoing_access_control`
+    // so workers spawned for this puncture inherit the correct producer.
+    pub(crate) spawner_flow_control_id: Option<FlowControlId>,
+    ice_config: IceConfig,
+    // The peer address the ICE connectivity checks settled on, if they have
+    // completed. Populated once by whichever pair's probe gets a response;
+    // `None` until then, in which case flow control falls back to the
+    // fixed `next` address `setup_flow_control` was originally given.
+    nominated_remote: RwLock<Option<Address>>,
 }
 
 impl fmt::Debug for UdpPunctureOptions {
@@ -19,12 +35,14 @@ impl fmt::Debug for UdpPunctureOptions {
 }
 
 impl UdpPunctureOptions {
-    /// Mark this UDP puncture as a Producer with a random [`FlowControlId`]
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
+    /// Mark this UDP puncture as a Producer with a random [`FlowControlId`],
+    /// configured for ICE-style candidate gathering and connectivity checks.
+    pub fn new(ice_config: IceConfig) -> Self {
         Self {
             flow_control_id: FlowControls::generate_flow_control_id(),
-            _spawner_flow_control_id: None,
+            spawner_flow_control_id: None,
+            ice_config,
+            nominated_remote: RwLock::new(None),
         }
     }
 
@@ -32,6 +50,81 @@ impl UdpPunctureOptions {
     pub fn producer_flow_control_id(&self) -> FlowControlId {
         self.flow_control_id.clone()
     }
+
+    /// Mark this puncture's consumer as spawned by a spawner with the given
+    /// [`FlowControlId`], so workers it spawns inherit the correct producer
+    /// relationship instead of accepting messages from anywhere.
+    pub(crate) fn set_spawner_flow_control_id(&mut self, flow_control_id: FlowControlId) {
+        self.spawner_flow_control_id = Some(flow_control_id);
+    }
+
+    /// Configuration this puncture gathers candidates and runs
+    /// connectivity checks with.
+    pub fn ice_config(&self) -> &IceConfig {
+        &self.ice_config
+    }
+
+    /// Record the address the ICE connectivity checks nominated, once the
+    /// first candidate pair's probe round-trips successfully. Keepalives on
+    /// this pair, and a fresh round of checks if it goes silent, are the
+    /// caller's responsibility to keep the nomination current.
+    pub(crate) fn nominate_remote_address(&self, address: Address) {
+        *self.nominated_remote.write().unwrap() = Some(address);
+    }
+
+    /// Run connectivity checks over every local/remote candidate pair and,
+    /// if one of them round-trips, nominate its remote address so the next
+    /// [`setup_flow_control`](Self::setup_flow_control) call prefers it over
+    /// the fixed `next` address. Returns whether a pair was nominated.
+    ///
+    /// `probe` is handed to [`run_connectivity_checks`] with each call bounded
+    /// by `ice_config().check_timeout`: a probe that has not answered by then
+    /// counts as failed and the next pair is tried. Actually sending a datagram and waiting
+    /// for a reply over a live UDP socket is the caller's responsibility.
+    /// Bounded by `ice_config().gather_timeout`: if no pair has responded by
+    /// then, this gives up and reports nothing nominated rather than
+    /// checking the remaining pairs indefinitely.
+    ///
+    /// Note: this crate slice has no UDP puncture worker wired up to supply
+    /// a real `probe` or to actually exchange candidates over a signaling
+    /// channel beforehand - the gather/exchange/keepalive loop the original
+    /// request describes has no live call site to attach to here. This
+    /// function is the pairing/nomination logic that call site would use.
+    pub(crate) async fn run_ice_and_nominate<F, Fut>(
+        &self,
+        local: &CandidateList,
+        remote: &CandidateList,
+        probe: F,
+    ) -> Result<bool>
+    where
+        F: FnMut(Candidate, Candidate) -> Fut,
+        Fut: core::future::Future<Output = Result<bool>>,
+    {
+        let check_timeout = self.ice_config.check_timeout;
+        let mut probe = probe;
+        let timed_probe = move |local: Candidate, remote: Candidate| {
+            let attempt = probe(local, remote);
+            async move {
+                match tokio::time::timeout(check_timeout, attempt).await {
+                    Ok(result) => result,
+                    Err(_) => Ok(false),
+                }
+            }
+        };
+        let checks = run_connectivity_checks(local, remote, timed_probe);
+        let outcome = match tokio::time::timeout(self.ice_config.gather_timeout, checks).await {
+            Ok(result) => result?,
+            Err(_) => None,
+        };
+
+        match outcome {
+            Some(pair) => {
+                self.nominate_remote_address(Address::from(pair.remote.address.to_string()));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 }
 
 impl UdpPunctureOptions {
@@ -41,6 +134,13 @@ impl UdpPunctureOptions {
         addresses: &Addresses,
         next: &Address,
     ) -> Result<()> {
+        // Prefer the address ICE settled on over the fixed `next` we were
+        // given at construction time: with multiple candidates in play, the
+        // pair that actually punched through the NAT may not be the one
+        // either side started out assuming.
+        let nominated = self.nominated_remote.read().unwrap().clone();
+        let next = nominated.as_ref().unwrap_or(next);
+
         if let Some(flow_control_id) = flow_controls
             .find_flow_control_with_producer_address(next)
             .map(|x| x.flow_control_id().clone())
@@ -52,24 +152,213 @@ impl UdpPunctureOptions {
         flow_controls.add_producer(
             addresses.receiver_address().clone(),
             &self.flow_control_id,
-            None,
+            // Tag this producer as spawned under the spawner's flow, if any,
+            // so its consumers inherit the spawner's relationship instead of
+            // only ever being reachable through this puncture's own flow.
+            self.spawner_flow_control_id.clone(),
             vec![addresses.sender_address().clone()],
         );
 
         Ok(())
     }
 
+    /// Restrict a punctured receiver to only relay messages tagged with
+    /// this puncture's `flow_control_id`, closing off the confused-deputy
+    /// gap where it would otherwise forward anything it receives to any
+    /// address.
     pub(crate) fn create_receiver_outgoing_access_control(
         &self,
-        _flow_controls: &FlowControls,
+        flow_controls: &FlowControls,
     ) -> Arc<dyn OutgoingAccessControl> {
-        // FIXME: PUNCTURE
-        // let ac = FlowControlOutgoingAccessControl::new(
-        //     flow_controls,
-        //     self.flow_control_id.clone(),
-        //     None,
-        // );
-
-        Arc::new(AllowAll)
+        let ac = FlowControlOutgoingAccessControl::new(
+            flow_controls,
+            self.flow_control_id.clone(),
+            self.spawner_flow_control_id.clone(),
+        );
+
+        Arc::new(ac)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ockam_core::route;
+    use ockam_core::routing::RelayMessage;
+
+    #[ockam_macros::test]
+    async fn receiver_access_control_rejects_destinations_outside_the_flow(
+        _ctx: &mut ockam_core::Context,
+    ) -> Result<()> {
+        let flow_controls = FlowControls::new();
+        let options = UdpPunctureOptions::new(IceConfig::default());
+
+        let receiver_address = Address::from("receiver");
+        let allowed_consumer = Address::from("allowed_consumer");
+        let outside_address = Address::from("outside_the_flow");
+
+        flow_controls.add_producer(
+            receiver_address.clone(),
+            &options.flow_control_id,
+            None,
+            vec![],
+        );
+        flow_controls.add_consumer(allowed_consumer.clone(), &options.flow_control_id);
+
+        let ac = options.create_receiver_outgoing_access_control(&flow_controls);
+
+        let allowed_msg = RelayMessage::new(
+            receiver_address.clone(),
+            allowed_consumer,
+            vec![],
+            route![],
+        );
+        assert!(ac.is_authorized(&allowed_msg).await?);
+
+        // An address that was never added as a consumer of this puncture's
+        // flow must not be reachable through it, even though the message
+        // originates from the puncture's own receiver.
+        let rejected_msg = RelayMessage::new(receiver_address, outside_address, vec![], route![]);
+        assert!(!ac.is_authorized(&rejected_msg).await?);
+
+        Ok(())
+    }
+
+    #[ockam_macros::test]
+    async fn spawned_receiver_inherits_the_spawner_relationship_but_nothing_wider(
+        _ctx: &mut ockam_core::Context,
+    ) -> Result<()> {
+        let flow_controls = FlowControls::new();
+
+        // A spawner (e.g. the listener that accepts incoming puncture
+        // requests) already has its own flow, with one legitimate consumer.
+        let spawner_flow_control_id = FlowControls::generate_flow_control_id();
+        let spawner_address = Address::from("spawner");
+        let spawner_consumer = Address::from("spawner_consumer");
+        let outside_address = Address::from("outside_the_spawner_relationship");
+        flow_controls.add_producer(spawner_address, &spawner_flow_control_id, None, vec![]);
+        flow_controls.add_consumer(spawner_consumer.clone(), &spawner_flow_control_id);
+
+        let mut options = UdpPunctureOptions::new(IceConfig::default());
+        options.set_spawner_flow_control_id(spawner_flow_control_id.clone());
+
+        // A worker this puncture spawns is registered the same way
+        // `setup_flow_control` registers its receiver: as a producer tagged
+        // with the spawner's flow control id.
+        let receiver_address = Address::from("spawned_receiver");
+        flow_controls.add_producer(
+            receiver_address.clone(),
+            &options.flow_control_id,
+            options.spawner_flow_control_id.clone(),
+            vec![],
+        );
+
+        let ac = options.create_receiver_outgoing_access_control(&flow_controls);
+
+        // Without the fix, `spawner_flow_control_id` was never threaded
+        // through, so a consumer registered only under the *spawner's* flow
+        // (as every legitimate peer of a spawned worker is) would be
+        // rejected - the confused-deputy gap the request called out.
+        let allowed_msg = RelayMessage::new(
+            receiver_address.clone(),
+            spawner_consumer,
+            vec![],
+            route![],
+        );
+        assert!(ac.is_authorized(&allowed_msg).await?);
+
+        // An address that is neither this puncture's own consumer nor the
+        // spawner's must still be unreachable.
+        let rejected_msg = RelayMessage::new(receiver_address, outside_address, vec![], route![]);
+        assert!(!ac.is_authorized(&rejected_msg).await?);
+
+        Ok(())
+    }
+
+    #[ockam_macros::test]
+    async fn run_ice_and_nominate_prefers_the_highest_priority_pair_that_responds(
+        _ctx: &mut ockam_core::Context,
+    ) -> Result<()> {
+        use std::net::SocketAddr;
+
+        let host: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let relayed: SocketAddr = "127.0.0.1:2000".parse().unwrap();
+
+        let local = CandidateList::new(vec![Candidate::new(host, CandidateKind::Host)]);
+        let remote = CandidateList::new(vec![
+            Candidate::new(relayed, CandidateKind::Relayed),
+            Candidate::new(host, CandidateKind::Host),
+        ]);
+
+        let options = UdpPunctureOptions::new(IceConfig::default());
+
+        // Only the `Relayed` candidate ever responds: the higher-priority
+        // `Host` pair must still be tried (and fail) first.
+        let tried = core::cell::RefCell::new(Vec::new());
+        let nominated = options
+            .run_ice_and_nominate(&local, &remote, |local, remote| {
+                tried.borrow_mut().push((local.address, remote.address));
+                async move { Ok(remote.kind == CandidateKind::Relayed) }
+            })
+            .await?;
+
+        assert!(nominated);
+        assert_eq!(*tried.borrow(), vec![(host, host), (host, relayed)]);
+        assert_eq!(
+            *options.nominated_remote.read().unwrap(),
+            Some(Address::from(relayed.to_string()))
+        );
+
+        Ok(())
+    }
+
+    #[ockam_macros::test]
+    async fn run_ice_and_nominate_gives_up_after_the_gather_timeout(
+        _ctx: &mut ockam_core::Context,
+    ) -> Result<()> {
+        use std::net::SocketAddr;
+
+        let host: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let local = CandidateList::new(vec![Candidate::new(host, CandidateKind::Host)]);
+        let remote = CandidateList::new(vec![Candidate::new(host, CandidateKind::Host)]);
+
+        let options = UdpPunctureOptions::new(IceConfig {
+            gather_timeout: core::time::Duration::from_millis(10),
+            ..IceConfig::default()
+        });
+
+        // The probe never resolves within the gather timeout, so this must
+        // give up and report nothing nominated instead of waiting forever.
+        let nominated = options
+            .run_ice_and_nominate(&local, &remote, |_, _| async {
+                core::future::pending::<Result<bool>>().await
+            })
+            .await?;
+
+        assert!(!nominated);
+        assert!(options.nominated_remote.read().unwrap().is_none());
+
+        Ok(())
+    }
+
+    #[ockam_macros::test]
+    async fn run_ice_and_nominate_leaves_nothing_nominated_when_no_pair_responds(
+        _ctx: &mut ockam_core::Context,
+    ) -> Result<()> {
+        use std::net::SocketAddr;
+
+        let host: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let local = CandidateList::new(vec![Candidate::new(host, CandidateKind::Host)]);
+        let remote = CandidateList::new(vec![Candidate::new(host, CandidateKind::Host)]);
+
+        let options = UdpPunctureOptions::new(IceConfig::default());
+        let nominated = options
+            .run_ice_and_nominate(&local, &remote, |_, _| async { Ok(false) })
+            .await?;
+
+        assert!(!nominated);
+        assert!(options.nominated_remote.read().unwrap().is_none());
+
+        Ok(())
     }
 }
\ No newline at end of file
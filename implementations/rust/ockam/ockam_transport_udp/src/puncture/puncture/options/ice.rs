@@ -0,0 +1,222 @@
+use core::time::Duration;
+use std::net::SocketAddr;
+
+use ockam_core::compat::vec::Vec;
+use ockam_core::{Address, Result};
+
+/// How a [`Candidate`] address was learned, ranked in the same order ICE
+/// (RFC 8445) prefers them: a directly reachable host address first, a
+/// server-reflexive address learned by asking the relay what we look like
+/// from the outside next, and a fully relayed address last, since every
+/// datagram on it costs the relay bandwidth.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum CandidateKind {
+    Relayed,
+    ServerReflexive,
+    Host,
+}
+
+/// A transport address one side offers as a place the other side might be
+/// able to reach it.
+#[derive(Clone, Copy, Debug)]
+pub struct Candidate {
+    pub address: SocketAddr,
+    pub kind: CandidateKind,
+}
+
+impl Candidate {
+    pub fn new(address: SocketAddr, kind: CandidateKind) -> Self {
+        Self { address, kind }
+    }
+}
+
+/// The candidates gathered for one side of a puncture, in priority order
+/// (highest first), ready to be exchanged with the peer over the existing
+/// signaling channel.
+#[derive(Clone, Debug, Default)]
+pub struct CandidateList {
+    candidates: Vec<Candidate>,
+}
+
+impl CandidateList {
+    pub fn new(mut candidates: Vec<Candidate>) -> Self {
+        candidates.sort_by_key(|c| core::cmp::Reverse(c.kind));
+        Self { candidates }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Candidate> {
+        self.candidates.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
+
+/// Configuration for ICE-style candidate gathering and connectivity checks.
+#[derive(Clone, Debug)]
+pub struct IceConfig {
+    /// Address of the Ockam relay used as a STUN-style rendezvous to learn
+    /// our server-reflexive address.
+    pub rendezvous_address: Option<Address>,
+    /// Address to fall back to, relaying every datagram, if no direct pair
+    /// can be punched through.
+    pub relay_fallback_address: Option<Address>,
+    /// How long to wait for candidate gathering to complete. Also bounds the
+    /// whole round of connectivity checks in
+    /// `UdpPunctureOptions::run_ice_and_nominate`.
+    pub gather_timeout: Duration,
+    /// How long to wait for a probe datagram to be acknowledged before
+    /// trying the next candidate pair.
+    pub check_timeout: Duration,
+    /// How often to send a keepalive datagram on the nominated pair.
+    pub keepalive_interval: Duration,
+}
+
+impl Default for IceConfig {
+    fn default() -> Self {
+        Self {
+            rendezvous_address: None,
+            relay_fallback_address: None,
+            gather_timeout: Duration::from_secs(5),
+            check_timeout: Duration::from_millis(500),
+            keepalive_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+/// The outcome of pairing up and checking every local/remote candidate
+/// combination: the first pair that completed a round trip, nominated as
+/// the one the puncture will actually use.
+#[derive(Clone, Copy, Debug)]
+pub struct NominatedPair {
+    pub local: Candidate,
+    pub remote: Candidate,
+}
+
+/// Generates every local/remote candidate pair to check, highest-priority
+/// pairs first, so the best pair is (usually) also the first one nominated.
+pub fn candidate_pairs<'a>(
+    local: &'a CandidateList,
+    remote: &'a CandidateList,
+) -> Vec<(&'a Candidate, &'a Candidate)> {
+    let mut pairs: Vec<(&Candidate, &Candidate)> = Vec::new();
+    for l in local.iter() {
+        for r in remote.iter() {
+            pairs.push((l, r));
+        }
+    }
+    pairs.sort_by_key(|(l, r)| core::cmp::Reverse((l.kind, r.kind)));
+    pairs
+}
+
+/// Run paired connectivity checks by sending a probe datagram on every
+/// local/remote candidate pair (highest-priority pairs first) and
+/// nominating the first pair whose probe gets a response.
+///
+/// `probe` is injected so the caller decides how a probe is actually sent
+/// and awaited (a real UDP socket in production, a channel in tests); this
+/// function only owns the pairing and nomination logic.
+pub async fn run_connectivity_checks<F, Fut>(
+    local: &CandidateList,
+    remote: &CandidateList,
+    mut probe: F,
+) -> Result<Option<NominatedPair>>
+where
+    F: FnMut(Candidate, Candidate) -> Fut,
+    Fut: core::future::Future<Output = Result<bool>>,
+{
+    for (local, remote) in candidate_pairs(local, remote) {
+        if probe(*local, *remote).await? {
+            return Ok(Some(NominatedPair {
+                local: *local,
+                remote: *remote,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        format!("127.0.0.1:{port}").parse().unwrap()
+    }
+
+    #[test]
+    fn candidate_list_sorts_highest_priority_first() {
+        let list = CandidateList::new(vec![
+            Candidate::new(addr(1), CandidateKind::Relayed),
+            Candidate::new(addr(2), CandidateKind::Host),
+            Candidate::new(addr(3), CandidateKind::ServerReflexive),
+        ]);
+
+        let kinds: Vec<_> = list.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                CandidateKind::Host,
+                CandidateKind::ServerReflexive,
+                CandidateKind::Relayed,
+            ]
+        );
+    }
+
+    #[test]
+    fn candidate_pairs_are_ordered_by_combined_priority() {
+        let local = CandidateList::new(vec![
+            Candidate::new(addr(1), CandidateKind::Host),
+            Candidate::new(addr(2), CandidateKind::Relayed),
+        ]);
+        let remote = CandidateList::new(vec![
+            Candidate::new(addr(3), CandidateKind::ServerReflexive),
+            Candidate::new(addr(4), CandidateKind::Host),
+        ]);
+
+        let pairs = candidate_pairs(&local, &remote);
+        let kinds: Vec<_> = pairs.iter().map(|(l, r)| (l.kind, r.kind)).collect();
+
+        // Host/Host outranks Host/ServerReflexive, which outranks
+        // Relayed/Host, which outranks Relayed/ServerReflexive.
+        assert_eq!(
+            kinds,
+            vec![
+                (CandidateKind::Host, CandidateKind::Host),
+                (CandidateKind::Host, CandidateKind::ServerReflexive),
+                (CandidateKind::Relayed, CandidateKind::Host),
+                (CandidateKind::Relayed, CandidateKind::ServerReflexive),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn run_connectivity_checks_nominates_the_first_pair_that_responds() {
+        let local = CandidateList::new(vec![Candidate::new(addr(1), CandidateKind::Host)]);
+        let remote = CandidateList::new(vec![
+            Candidate::new(addr(2), CandidateKind::Relayed),
+            Candidate::new(addr(3), CandidateKind::Host),
+        ]);
+
+        let nominated = run_connectivity_checks(&local, &remote, |_, remote| async move {
+            Ok(remote.kind == CandidateKind::Relayed)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(nominated.unwrap().remote.address, addr(2));
+    }
+
+    #[tokio::test]
+    async fn run_connectivity_checks_returns_none_when_every_probe_fails() {
+        let local = CandidateList::new(vec![Candidate::new(addr(1), CandidateKind::Host)]);
+        let remote = CandidateList::new(vec![Candidate::new(addr(2), CandidateKind::Host)]);
+
+        let nominated = run_connectivity_checks(&local, &remote, |_, _| async { Ok(false) })
+            .await
+            .unwrap();
+
+        assert!(nominated.is_none());
+    }
+}